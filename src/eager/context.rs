@@ -1,22 +1,203 @@
-use std::ffi::CStr;
+use std::env;
+use std::error::Error;
+use std::ffi::{CStr, CString};
+use std::fmt::{self, Display, Formatter};
+use std::sync::RwLock;
 
 use tensorflow_sys as tf;
 
-use crate::{Device, Result, Status};
+use crate::{Device, OwnedStatus, Result, Status};
+
+/// Environment and option state captured when `ContextOptions` are built,
+/// reported if context creation fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextDiagnostics {
+    /// Whether this crate was built with the `tensorflow_gpu` feature.
+    ///
+    /// This is a build-time setting; it does not mean that GPU libraries were
+    /// found at runtime.
+    pub tensorflow_gpu_feature: bool,
+    /// Whether the CUDA runtime library (`libcudart`) could be loaded, or
+    /// `None` if probing is not supported on this platform.
+    pub cuda_runtime_found: Option<bool>,
+    /// Whether the CUDA driver library (`libcuda`) could be loaded, or `None`
+    /// if probing is not supported on this platform.
+    pub cuda_driver_found: Option<bool>,
+    /// Raw value of `CUDA_VISIBLE_DEVICES` when the options were created, or
+    /// `None` if it was unset.
+    pub cuda_visible_devices: Option<String>,
+    /// Number of GPUs made visible by `CUDA_VISIBLE_DEVICES`, or `None` if it
+    /// was unset (in which case all GPUs are visible).
+    pub visible_device_count: Option<usize>,
+    /// Size in bytes of the serialized `ConfigProto`, or `None` if no config
+    /// was set.
+    pub config_len: Option<usize>,
+}
+
+impl ContextDiagnostics {
+    fn capture() -> Self {
+        let cuda_visible_devices = env::var("CUDA_VISIBLE_DEVICES").ok();
+        ContextDiagnostics {
+            tensorflow_gpu_feature: cfg!(feature = "tensorflow_gpu"),
+            cuda_runtime_found: probe_library(&[
+                "libcudart.so",
+                "libcudart.so.12",
+                "libcudart.so.11.0",
+            ]),
+            cuda_driver_found: probe_library(&["libcuda.so.1", "libcuda.so"]),
+            visible_device_count: visible_device_count(cuda_visible_devices.as_deref()),
+            cuda_visible_devices,
+            config_len: None,
+        }
+    }
+}
+
+/// Returns whether any of `names` can be loaded with `dlopen`.
+#[cfg(unix)]
+fn probe_library(names: &[&str]) -> Option<bool> {
+    let found = names.iter().any(|name| {
+        let c_name = match CString::new(*name) {
+            Ok(c_name) => c_name,
+            Err(_) => return false,
+        };
+        unsafe {
+            let handle = libc::dlopen(c_name.as_ptr(), libc::RTLD_LAZY | libc::RTLD_LOCAL);
+            if handle.is_null() {
+                false
+            } else {
+                libc::dlclose(handle);
+                true
+            }
+        }
+    });
+    Some(found)
+}
+
+/// Returns whether any of `names` can be loaded with `dlopen`.
+#[cfg(not(unix))]
+fn probe_library(_names: &[&str]) -> Option<bool> {
+    None
+}
+
+/// Counts the devices listed in a `CUDA_VISIBLE_DEVICES` value.
+///
+/// As in CUDA, entries after the first invalid one (e.g. `-1`) are ignored, so
+/// `-1` or an empty value hide every GPU.
+fn visible_device_count(cuda_visible_devices: Option<&str>) -> Option<usize> {
+    cuda_visible_devices.map(|devices| {
+        devices
+            .split(',')
+            .map(str::trim)
+            .take_while(|d| !d.is_empty() && !d.starts_with('-'))
+            .count()
+    })
+}
+
+fn found_str(found: Option<bool>) -> &'static str {
+    match found {
+        Some(true) => "found",
+        Some(false) => "not found",
+        None => "unknown",
+    }
+}
+
+impl Display for ContextDiagnostics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tensorflow_gpu feature: {}, ",
+            if self.tensorflow_gpu_feature {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        )?;
+        write!(
+            f,
+            "libcudart: {}, libcuda: {}, ",
+            found_str(self.cuda_runtime_found),
+            found_str(self.cuda_driver_found)
+        )?;
+        match (&self.cuda_visible_devices, self.visible_device_count) {
+            (Some(devices), Some(count)) => write!(
+                f,
+                "CUDA_VISIBLE_DEVICES: {:?} ({} visible), ",
+                devices, count
+            )?,
+            _ => f.write_str("CUDA_VISIBLE_DEVICES: unset, ")?,
+        }
+        match self.config_len {
+            Some(len) => write!(f, "config: {} bytes", len),
+            None => f.write_str("config: default"),
+        }
+    }
+}
+
+/// Error returned by `Context::new_with_diagnostics`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextError {
+    /// The code and message reported by TensorFlow.
+    pub status: OwnedStatus,
+    /// The state of the environment and options at the time of the failure.
+    pub diagnostics: ContextDiagnostics,
+}
+
+impl Display for ContextError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: Failed to create eager context: {} ({})",
+            self.status.code, self.status.message, self.diagnostics
+        )
+    }
+}
+
+impl Error for ContextError {}
+
+impl From<ContextError> for Status {
+    fn from(e: ContextError) -> Self {
+        Status::new_set_lossy(
+            e.status.code,
+            &format!(
+                "Failed to create eager context: {} ({})",
+                e.status.message, e.diagnostics
+            ),
+        )
+    }
+}
 
 /// Options that can be passed during context creation.
 #[derive(Debug)]
 pub struct ContextOptions {
     inner: *mut tf::TFE_ContextOptions,
+    diagnostics: ContextDiagnostics,
 }
-impl_new!(
-    ContextOptions,
-    TFE_NewContextOptions,
-    "Creates a blank set of context options."
-);
+
+impl Default for ContextOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl_drop!(ContextOptions, TFE_DeleteContextOptions);
 
 impl ContextOptions {
+    /// Creates a blank set of context options.
+    pub fn new() -> Self {
+        let inner = unsafe { tf::TFE_NewContextOptions() };
+        assert!(!inner.is_null());
+        ContextOptions {
+            inner,
+            diagnostics: ContextDiagnostics::capture(),
+        }
+    }
+
+    /// Returns the diagnostics that will be reported if creating a context
+    /// from these options fails.
+    pub fn diagnostics(&self) -> &ContextDiagnostics {
+        &self.diagnostics
+    }
+
     /// Set the config.
     ///
     /// `config` should be a serialized [`ConfigProto` proto](https://github.com/tensorflow/tensorflow/blob/master/tensorflow/core/protobuf/config.proto).
@@ -31,7 +212,9 @@ impl ContextOptions {
                 status.inner(),
             );
         }
-        status.into_result()
+        status.into_result()?;
+        self.diagnostics.config_len = Some(config.len());
+        Ok(())
     }
 
     /// Sets the default execution mode (sync/async).
@@ -46,22 +229,86 @@ impl ContextOptions {
 #[derive(Debug)]
 pub struct Context {
     pub(crate) inner: *mut tf::TFE_Context,
+    /// Device applied to every op created under this context, unless the op
+    /// sets its own device.
+    pub(crate) default_device: RwLock<Option<CString>>,
 }
 impl_drop!(Context, TFE_DeleteContext);
 
 impl Context {
     /// Create a Context
+    ///
+    /// On failure, the returned status keeps the code reported by TensorFlow,
+    /// and its message is extended with the `ContextDiagnostics` of `opts`.
+    /// The return type is kept as `Status` for compatibility; use
+    /// `new_with_diagnostics` to inspect the diagnostics as fields.
     pub fn new(opts: ContextOptions) -> Result<Self> {
+        Self::new_with_diagnostics(opts).map_err(Status::from)
+    }
+
+    /// Create a Context, returning a structured `ContextError` on failure.
+    pub fn new_with_diagnostics(opts: ContextOptions) -> std::result::Result<Self, ContextError> {
         let status = Status::new();
 
         let inner = unsafe { tf::TFE_NewContext(opts.inner, status.inner) };
         if inner.is_null() {
-            Err(status)
+            Err(ContextError {
+                status: status.to_owned_error(),
+                diagnostics: opts.diagnostics.clone(),
+            })
         } else {
-            Ok(Context { inner })
+            Ok(Context {
+                inner,
+                default_device: RwLock::new(None),
+            })
         }
     }
 
+    /// Sets the default device on which ops executed under this context are
+    /// placed.
+    ///
+    /// Ops that set a device explicitly (e.g. with a raw op's `set_device`)
+    /// still run on that device. Passing an empty string clears the default.
+    ///
+    /// Returns an error, leaving the current default unchanged, if `device` is
+    /// not a valid device specification.
+    pub fn set_default_device(&self, device: &str) -> Result<()> {
+        let device = if device.is_empty() {
+            None
+        } else {
+            let c_device = CString::new(device)?;
+            self.check_device_spec(&c_device)?;
+            Some(c_device)
+        };
+        *self.default_device.write().unwrap() = device;
+        Ok(())
+    }
+
+    /// Checks that `device` parses as a device specification by setting it on
+    /// a throwaway `NoOp`.
+    fn check_device_spec(&self, device: &CStr) -> Result<()> {
+        let status = Status::new();
+        let op_name = CString::new("NoOp")?;
+        unsafe {
+            let op = tf::TFE_NewOp(self.inner, op_name.as_ptr(), status.inner);
+            if op.is_null() || !status.is_ok() {
+                return Err(status);
+            }
+            tf::TFE_OpSetDevice(op, device.as_ptr(), status.inner);
+            tf::TFE_DeleteOp(op);
+        }
+        status.into_result()
+    }
+
+    /// Returns the default device set with `set_default_device`, if any.
+    pub fn default_device(&self) -> Option<String> {
+        self.default_device
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|d| d.to_string_lossy().into_owned())
+    }
+
     /// Lists all devices in a context.
     pub fn device_list(&self) -> Result<Vec<Device>> {
         let status = Status::new();
@@ -118,6 +365,7 @@ unsafe impl std::marker::Sync for Context {}
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::Code;
 
     #[test]
     fn test_create_context() {
@@ -153,6 +401,104 @@ mod test {
         Context::new(opts).unwrap();
     }
 
+    #[test]
+    fn test_context_bogus_config() {
+        use crate::protos::config::ConfigProto;
+        use protobuf::Message;
+
+        // A parseable config which leaves no CPU device, so context creation
+        // fails.
+        let mut config = ConfigProto::new();
+        config.mut_device_count().insert("CPU".to_string(), 0);
+        let mut buf = vec![];
+        config.write_to_writer(&mut buf).unwrap();
+
+        let mut opts = ContextOptions::new();
+        opts.set_config(&buf).unwrap();
+        assert_eq!(opts.diagnostics().config_len, Some(buf.len()));
+        let expected_diagnostics = opts.diagnostics().clone();
+        let err = Context::new_with_diagnostics(opts).unwrap_err();
+        assert_ne!(err.status.code, Code::Ok);
+        assert!(!err.status.message.is_empty());
+        assert_eq!(err.diagnostics, expected_diagnostics);
+
+        let mut opts = ContextOptions::new();
+        opts.set_config(&buf).unwrap();
+        let diagnostics = opts.diagnostics().to_string();
+        let status = Context::new(opts).unwrap_err();
+        assert_ne!(status.code(), Code::Ok);
+        let msg = status.to_string();
+        assert!(msg.contains("Failed to create eager context"), "{}", msg);
+        assert!(msg.contains(&diagnostics), "{}", msg);
+        assert!(
+            msg.contains(&format!("config: {} bytes", buf.len())),
+            "{}",
+            msg
+        );
+    }
+
+    #[test]
+    fn test_context_diagnostics_display() {
+        let mut diagnostics = ContextDiagnostics {
+            tensorflow_gpu_feature: false,
+            cuda_runtime_found: Some(true),
+            cuda_driver_found: Some(false),
+            cuda_visible_devices: Some("-1".to_string()),
+            visible_device_count: Some(0),
+            config_len: Some(12),
+        };
+        assert_eq!(
+            diagnostics.to_string(),
+            "tensorflow_gpu feature: disabled, libcudart: found, libcuda: not found, \
+             CUDA_VISIBLE_DEVICES: \"-1\" (0 visible), config: 12 bytes"
+        );
+
+        diagnostics.cuda_runtime_found = None;
+        diagnostics.cuda_driver_found = None;
+        diagnostics.cuda_visible_devices = None;
+        diagnostics.visible_device_count = None;
+        diagnostics.config_len = None;
+        assert_eq!(
+            diagnostics.to_string(),
+            "tensorflow_gpu feature: disabled, libcudart: unknown, libcuda: unknown, \
+             CUDA_VISIBLE_DEVICES: unset, config: default"
+        );
+    }
+
+    #[test]
+    fn test_visible_device_count() {
+        assert_eq!(visible_device_count(None), None);
+        assert_eq!(visible_device_count(Some("")), Some(0));
+        assert_eq!(visible_device_count(Some("-1")), Some(0));
+        assert_eq!(visible_device_count(Some("0")), Some(1));
+        assert_eq!(visible_device_count(Some("0, 1")), Some(2));
+        assert_eq!(visible_device_count(Some("0,-1,2")), Some(1));
+        assert_eq!(visible_device_count(Some("GPU-8932f937,1")), Some(2));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probe_library() {
+        assert_eq!(probe_library(&["libdoes-not-exist.so"]), Some(false));
+    }
+
+    #[test]
+    fn test_default_device() {
+        let ctx = Context::new(ContextOptions::new()).unwrap();
+        assert_eq!(ctx.default_device(), None);
+        ctx.set_default_device("/job:localhost/replica:0/task:0/device:CPU:0")
+            .unwrap();
+        assert_eq!(
+            ctx.default_device().as_deref(),
+            Some("/job:localhost/replica:0/task:0/device:CPU:0")
+        );
+        ctx.set_default_device("").unwrap();
+        assert_eq!(ctx.default_device(), None);
+        assert!(ctx.set_default_device("bad\0device").is_err());
+        assert!(ctx.set_default_device("gpu0").is_err());
+        assert_eq!(ctx.default_device(), None);
+    }
+
     #[test]
     fn test_device_list() {
         let opts = ContextOptions::new();
@@ -5,14 +5,14 @@ use libc::c_int;
 use libc::c_uchar;
 use libc::c_void;
 use libc::size_t;
+use std::collections::HashSet;
 use std::ffi::{CStr, CString};
-use std::marker::PhantomData;
-use std::mem::{self, ManuallyDrop};
+use std::mem;
 use std::os::raw::c_void as std_c_void;
 use std::ptr;
 
 use crate::eager::{Context, TensorHandle};
-use crate::{AnyTensor, Code, DataType, Result, Shape, Status};
+use crate::{AnyTensor, AttrType, Code, DataType, Result, Shape, Status};
 
 use tensorflow_sys as tf;
 
@@ -35,7 +35,10 @@ pub mod raw_ops;
 #[derive(Debug)]
 struct Op<'a> {
     inner: *mut tf::TFE_Op,
-    ctx: PhantomData<&'a Context>,
+    ctx: &'a Context,
+    // Names of the attributes set through the `set_attr_*` methods. The C API
+    // has no way to query this.
+    set_attrs: HashSet<String>,
 }
 
 impl<'a> Drop for Op<'a> {
@@ -46,16 +49,6 @@ impl<'a> Drop for Op<'a> {
     }
 }
 
-/// Thin wrapper around a Context obtained from eager::Op.
-///
-/// Since the context taken from the Op is just a reference to the Context
-/// under which the Op was created, this wrapper is needed to ensure that the
-/// Context is not dropped here.
-struct OpContext<'a> {
-    ctx: ManuallyDrop<Context>,
-    lifetime: PhantomData<&'a Context>,
-}
-
 impl<'a> Op<'a> {
     fn new(ctx: &'a Context, op_or_function_name: &str) -> Result<Self> {
         let status = Status::new();
@@ -66,10 +59,21 @@ impl<'a> Op<'a> {
         if inner.is_null() || !status.is_ok() {
             return Err(status);
         }
-        Ok(Self {
+        let op = Self {
             inner,
-            ctx: PhantomData,
-        })
+            ctx,
+            set_attrs: HashSet::new(),
+        };
+
+        // Apply the context's default device. Callers may still override it
+        // with `set_device`.
+        if let Some(device_name) = ctx.default_device.read().unwrap().as_ref() {
+            unsafe {
+                tf::TFE_OpSetDevice(op.inner, device_name.as_ptr(), status.inner);
+            }
+            status.into_result()?;
+        }
+        Ok(op)
     }
 
     /// Returns the op or function name that this op will execute.
@@ -85,16 +89,8 @@ impl<'a> Op<'a> {
     }
 
     /// Return the context in which this op will be executed.
-    fn get_context(&self) -> Result<OpContext<'a>> {
-        let status = Status::new();
-        let inner = unsafe { tf::TFE_OpGetContext(self.inner, status.inner) };
-        status.into_result()?;
-
-        let ctx = ManuallyDrop::new(Context { inner });
-        Ok(OpContext {
-            ctx,
-            lifetime: PhantomData,
-        })
+    fn get_context(&self) -> &'a Context {
+        self.ctx
     }
 
     /// Adds an input to this operation.
@@ -126,6 +122,32 @@ impl<'a> Op<'a> {
         Ok(device_name)
     }
 
+    /// Returns the type of an attribute as declared by the op definition, and
+    /// whether the attribute holds a list of values.
+    ///
+    /// This does not indicate whether the attribute has been set. Returns an
+    /// error if the op has no attribute named `attr_name`.
+    fn attr_type(&self, attr_name: &str) -> Result<(AttrType, bool)> {
+        let status = Status::new();
+        let c_attr_name = CString::new(attr_name)?;
+        let mut is_list: c_uchar = 0;
+        let attr_type = unsafe {
+            tf::TFE_OpGetAttrType(self.inner, c_attr_name.as_ptr(), &mut is_list, status.inner)
+        };
+        status.into_result()?;
+        Ok((AttrType::from_c(attr_type), is_list != 0))
+    }
+
+    /// Returns true if the attribute has been set with one of the `set_attr_*`
+    /// methods.
+    ///
+    /// Attributes which TensorFlow infers from the inputs (e.g. `T` on `Add`)
+    /// are not counted, and unset attributes may still be filled in with their
+    /// default value at execution.
+    fn has_attr(&self, attr_name: &str) -> bool {
+        self.set_attrs.contains(attr_name)
+    }
+
     /// Adds multiple inputs to this operation.
     fn add_input_list(&mut self, inputs: &[TensorHandle]) -> Result<()> {
         let status = Status::new();
@@ -144,16 +166,17 @@ impl<'a> Op<'a> {
 
     /// Sets the value of a string attribute.
     fn set_attr_string(&mut self, attr_name: &str, value: &str) -> Result<()> {
-        let attr_name = CString::new(attr_name)?;
+        let c_attr_name = CString::new(attr_name)?;
         let c_value = value.as_bytes();
         unsafe {
             tf::TFE_OpSetAttrString(
                 self.inner,
-                attr_name.as_ptr(),
+                c_attr_name.as_ptr(),
                 c_value.as_ptr() as *const std_c_void,
                 c_value.len() as size_t,
             );
         }
+        self.set_attrs.insert(attr_name.to_string());
         Ok(())
     }
 
@@ -172,6 +195,7 @@ impl<'a> Op<'a> {
                 ptrs.len() as c_int,
             );
         }
+        self.set_attrs.insert(attr_name.to_string());
         Ok(())
     }
 
@@ -181,6 +205,7 @@ impl<'a> Op<'a> {
         unsafe {
             tf::TFE_OpSetAttrInt(self.inner, c_attr_name.as_ptr(), value);
         }
+        self.set_attrs.insert(attr_name.to_string());
         Ok(())
     }
 
@@ -195,6 +220,7 @@ impl<'a> Op<'a> {
                 value.len() as i32,
             );
         }
+        self.set_attrs.insert(attr_name.to_string());
         Ok(())
     }
 
@@ -204,6 +230,7 @@ impl<'a> Op<'a> {
         unsafe {
             tf::TFE_OpSetAttrFloat(self.inner, c_attr_name.as_ptr(), value);
         }
+        self.set_attrs.insert(attr_name.to_string());
         Ok(())
     }
 
@@ -220,6 +247,7 @@ impl<'a> Op<'a> {
                 c_value.len() as i32,
             );
         }
+        self.set_attrs.insert(attr_name.to_string());
         Ok(())
     }
 
@@ -229,6 +257,7 @@ impl<'a> Op<'a> {
         unsafe {
             tf::TFE_OpSetAttrBool(self.inner, c_attr_name.as_ptr(), if value { 1 } else { 0 });
         }
+        self.set_attrs.insert(attr_name.to_string());
         Ok(())
     }
 
@@ -244,6 +273,7 @@ impl<'a> Op<'a> {
                 c_value.len() as c_int,
             );
         }
+        self.set_attrs.insert(attr_name.to_string());
         Ok(())
     }
 
@@ -253,6 +283,7 @@ impl<'a> Op<'a> {
         unsafe {
             tf::TFE_OpSetAttrType(self.inner, c_attr_name.as_ptr(), value.to_c());
         }
+        self.set_attrs.insert(attr_name.to_string());
         Ok(())
     }
 
@@ -268,6 +299,7 @@ impl<'a> Op<'a> {
                 c_value.len() as i32,
             );
         }
+        self.set_attrs.insert(attr_name.to_string());
         Ok(())
     }
 
@@ -297,7 +329,9 @@ impl<'a> Op<'a> {
                 }
             }
         }
-        status.into_result()
+        status.into_result()?;
+        self.set_attrs.insert(attr_name.to_string());
+        Ok(())
     }
 
     /// Sets an attribute which holds an array of shapes.
@@ -337,7 +371,9 @@ impl<'a> Op<'a> {
                 status.inner,
             );
         }
-        status.into_result()
+        status.into_result()?;
+        self.set_attrs.insert(attr_name.to_string());
+        Ok(())
     }
 
     /// Sets a tensor-valued attribute.
@@ -352,7 +388,9 @@ impl<'a> Op<'a> {
                 status.inner(),
             );
         }
-        status.into_result()
+        status.into_result()?;
+        self.set_attrs.insert(attr_name.to_string());
+        Ok(())
     }
 
     /// Execute the operation defined by the `Op` and return hadndles to computed
@@ -482,6 +520,94 @@ mod tests {
         assert!(res.is_err());
     }
 
+    #[test]
+    fn test_default_device() {
+        let ctx = Context::new(ContextOptions::new()).unwrap();
+        let devices = ctx.device_list().unwrap();
+        let cpu_device = devices
+            .iter()
+            .find(|d| d.device_type == "CPU")
+            .expect("No CPU device was found.");
+        ctx.set_default_device(&cpu_device.name).unwrap();
+
+        let op = Op::new(&ctx, "Add").unwrap();
+        assert_eq!(op.get_device().unwrap(), cpu_device.name);
+
+        // A device set on the op overrides the default.
+        let mut op = Op::new(&ctx, "Add").unwrap();
+        op.set_device("/device:CPU:0").unwrap();
+        assert_eq!(op.get_device().unwrap(), "/device:CPU:0");
+
+        // The context obtained from the op still reports the default.
+        assert_eq!(op.get_context().default_device().unwrap(), cpu_device.name);
+
+        ctx.set_default_device("").unwrap();
+        let op = Op::new(&ctx, "Add").unwrap();
+        assert_eq!(op.get_device().unwrap(), "");
+    }
+
+    #[test]
+    fn test_malformed_default_device() {
+        let ctx = Context::new(ContextOptions::new()).unwrap();
+        assert!(ctx.set_default_device("gpu0").is_err());
+        assert_eq!(ctx.default_device(), None);
+
+        // The rejected name is not applied to later ops.
+        let op = Op::new(&ctx, "Add").unwrap();
+        assert_eq!(op.get_device().unwrap(), "");
+    }
+
+    #[test]
+    fn test_default_device_execute() {
+        let ctx = Context::new(ContextOptions::new()).unwrap();
+        let devices = ctx.device_list().unwrap();
+        let cpu_device = devices
+            .iter()
+            .find(|d| d.device_type == "CPU")
+            .expect("No CPU device was found.");
+        ctx.set_default_device(&cpu_device.name).unwrap();
+
+        let x = Tensor::new(&[2, 2])
+            .with_values(&[1i32, 2, 3, 4])
+            .unwrap()
+            .freeze();
+        let h_z = add(&ctx, &x, &x).unwrap();
+        assert_eq!(h_z.device_name().unwrap(), cpu_device.name);
+
+        let z = h_z.resolve::<i32>().unwrap();
+        let expected = Tensor::new(&[2, 2]).with_values(&[2i32, 4, 6, 8]).unwrap();
+        assert_eq!(z, expected);
+    }
+
+    #[test]
+    fn test_attr_type() {
+        let ctx = Context::new(ContextOptions::new()).unwrap();
+        let x = Tensor::from(1.0f32).freeze();
+        let h_x = TensorHandle::new(&ctx, &x).unwrap();
+
+        let mut op = Op::new(&ctx, "Add").unwrap();
+        op.add_input(&h_x).unwrap();
+        assert_eq!(op.attr_type("T").unwrap(), (AttrType::Type, false));
+        assert!(op.attr_type("no_such_attr").is_err());
+        // `T` is inferred from the input, but was not set explicitly.
+        assert!(!op.has_attr("T"));
+        op.set_attr_type("T", DataType::Float).unwrap();
+        assert!(op.has_attr("T"));
+
+        let mut op = Op::new(&ctx, "IdentityN").unwrap();
+        assert_eq!(op.attr_type("T").unwrap(), (AttrType::Type, true));
+        assert!(!op.has_attr("T"));
+        op.set_attr_type_list("T", &[DataType::Float]).unwrap();
+        assert!(op.has_attr("T"));
+
+        // A failed set is not recorded. Shapes over the 254 dimension limit
+        // are rejected.
+        let mut op = Op::new(&ctx, "Placeholder").unwrap();
+        let too_many_dims = Shape::from(Some(vec![Some(1); 255]));
+        assert!(op.set_attr_shape("shape", &too_many_dims).is_err());
+        assert!(!op.has_attr("shape"));
+    }
+
     #[test]
     fn test_add_ut() {
         let values = [1i32, 2, 3, 4];
@@ -693,4 +819,32 @@ mod tests {
             assert!((v0 - v1).abs() < f32::EPSILON);
         }
     }
+
+    #[cfg(feature = "tensorflow_gpu")]
+    #[test]
+    #[ignore]
+    fn test_default_device_gpu() {
+        let opts = ContextOptions::new();
+        let ctx = Context::new(opts).unwrap();
+        let devices = ctx.device_list().unwrap();
+        let gpu_device = devices
+            .iter()
+            .find(|d| d.device_type == "GPU")
+            .expect("No GPU device was found.");
+        let target_device = &gpu_device.name;
+        ctx.set_default_device(target_device).unwrap();
+
+        let x = Tensor::new(&[2, 2])
+            .with_values(&[1.0f32, 2.0, 3.0, 4.0])
+            .unwrap()
+            .freeze();
+        let h_z_gpu = add(&ctx, &x, &x).unwrap();
+        assert!(&h_z_gpu.device_name().unwrap() == target_device);
+
+        let z = h_z_gpu.resolve::<f32>().unwrap();
+        let expected = [2.0f32, 4.0, 6.0, 8.0];
+        for (v0, v1) in z.iter().zip(&expected) {
+            assert!((v0 - v1).abs() < f32::EPSILON);
+        }
+    }
 }
@@ -1,5 +1,6 @@
 use std::ffi::{CStr, CString};
 use std::marker::PhantomData;
+use std::sync::Arc;
 
 use tensorflow_sys as tf;
 
@@ -212,6 +213,18 @@ impl<'a> TensorHandle<'a> {
         unsafe { Ok(ReadonlyTensor::from_tf_tensor(tf_tensor).unwrap()) }
     }
 
+    /// Resolves the TensorHandle and copies its data into an `Arc<[T]>`.
+    ///
+    /// Unlike the tensor returned by `resolve`, the result does not alias memory
+    /// owned by TensorFlow, so it can be cloned cheaply and shared across threads.
+    /// Note that the shape of the tensor is not retained.
+    ///
+    /// This function will block till the operation that produces the current TensorHandle has completed.
+    pub fn resolve_shared<T: TensorType>(&self) -> Result<Arc<[T]>> {
+        let t = self.resolve::<T>()?;
+        Ok(Arc::from(&t[..]))
+    }
+
     /// Create a new TensorHandle with the same contents as the current TensorHandle but placed
     /// in the memory of the device name 'device_name'.
     /// If source and destination are the same device, then this creates a new handle
@@ -302,6 +315,32 @@ mod tests {
         assert_eq!(t, t2);
     }
 
+    #[test]
+    fn test_resolve_shared() {
+        let opts = ContextOptions::new();
+        let ctx = Context::new(opts).unwrap();
+
+        let t = Tensor::new(&[2, 3])
+            .with_values(&[0_i32, 1, 2, 3, 4, 5])
+            .unwrap()
+            .freeze();
+        let h = TensorHandle::new(&ctx, &t).unwrap();
+        let shared = h.resolve_shared::<i32>().unwrap();
+        assert_eq!(&shared[..], &t[..]);
+
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                std::thread::spawn(move || shared.iter().sum::<i32>())
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), 15);
+        }
+
+        assert!(h.resolve_shared::<f32>().is_err());
+    }
+
     /// Following tests are disabled by default because it requires a GPU and some setup.
     ///
     /// To run this test, you need to pass the `-- --ignored` argument to cargo test.
@@ -528,6 +528,22 @@ impl Status {
     fn inner(&mut self) -> *mut tf::TF_Status {
         self.inner
     }
+
+    /// Snapshots the code and message into an `OwnedStatus`, which does not
+    /// depend on the underlying C handle.
+    ///
+    /// Invalid UTF-8 in the message is replaced with `U+FFFD`.
+    pub fn to_owned_error(&self) -> OwnedStatus {
+        let message = unsafe {
+            CStr::from_ptr(tf::TF_Message(self.inner))
+                .to_string_lossy()
+                .into_owned()
+        };
+        OwnedStatus {
+            code: self.code(),
+            message,
+        }
+    }
 }
 
 impl Display for Status {
@@ -596,6 +612,33 @@ impl Error for Status {
     }
 }
 
+/// A plain Rust copy of a `Status`'s code and message.
+///
+/// Unlike `Status`, this holds no pointer into the C library, so it can be
+/// stored, cloned, and sent across threads freely. Created with
+/// `Status::to_owned_error`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedStatus {
+    /// The error code.
+    pub code: Code,
+    /// The error message.
+    pub message: String,
+}
+
+impl Display for OwnedStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl Error for OwnedStatus {}
+
+impl From<&Status> for OwnedStatus {
+    fn from(status: &Status) -> Self {
+        status.to_owned_error()
+    }
+}
+
 ////////////////////////
 
 /// Options that can be passed during session creation.
@@ -2416,6 +2459,19 @@ mod tests {
         assert_eq!(output_tensor[1], 6.0);
     }
 
+    #[test]
+    fn test_status_to_owned_error() {
+        let status = Status::new_set(Code::NotFound, "no such thing").unwrap();
+        let owned = status.to_owned_error();
+        assert_eq!(owned.code, Code::NotFound);
+        assert_eq!(owned.message, "no such thing");
+        assert_eq!(owned.to_string(), status.to_string());
+        assert_eq!(owned.to_string(), "NotFound: no such thing");
+        drop(status);
+        let handle = std::thread::spawn(move || owned.to_string());
+        assert_eq!(handle.join().unwrap(), "NotFound: no such thing");
+    }
+
     #[test]
     fn test_bfloat16() {
         let data = [-1.0f32, 0.0, 1.0, 2.5];